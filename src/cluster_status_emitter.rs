@@ -1,14 +1,16 @@
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use futures::future::join_all;
 use rdkafka::{admin::AdminClient, client::DefaultClientContext, ClientConfig};
 use tokio::{
-    sync::{broadcast, mpsc},
+    sync::{broadcast, mpsc, Semaphore},
     task::JoinHandle,
     time,
 };
 
 use crate::internals::Emitter;
-use crate::kafka_types::{Broker, TopicPartitionsStatus};
+use crate::kafka_types::{Broker, PartitionStatus, TopicPartitionsStatus};
 
 const CHANNEL_SIZE: usize = 1;
 const CHANNEL_SEND_TIMEOUT: Duration = Duration::from_millis(100);
@@ -16,6 +18,12 @@ const CHANNEL_SEND_TIMEOUT: Duration = Duration::from_millis(100);
 const METADATA_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
 const METADATA_FETCH_INTERVAL: Duration = Duration::from_secs(30);
 
+/// Upper bound on how many `fetch_watermarks` blocking calls run at once, across every
+/// topic/partition in the cluster combined. Without this, a cluster with thousands of partitions
+/// fires thousands of `spawn_blocking` tasks at the same instant every cycle, bursting against
+/// both the tokio blocking pool and the broker instead of just avoiding head-of-line blocking.
+const MAX_CONCURRENT_WATERMARK_FETCHES: usize = 64;
+
 /// Emits [`ClusterStatus`] via a provided [`mpsc::channel`].
 ///
 /// It wraps an Admin Kafka Client, regularly requests it for the cluster metadata,
@@ -49,6 +57,56 @@ impl ClusterStatusEmitter {
     }
 }
 
+/// Fetch the begin/end offset watermarks for every partition of `tps`, concurrently.
+///
+/// Each partition's lookup runs on the blocking thread pool via [`tokio::task::spawn_blocking`],
+/// so `fetch_watermarks`'s blocking, per-partition 10s-timeout call can no longer stall the rest
+/// of the partitions behind it: one slow or failing partition only delays itself. `semaphore` is
+/// shared across every topic in the cluster (not just this one), so the number of these blocking
+/// calls in flight at once is bounded regardless of how many thousands of partitions exist.
+async fn fetch_topic_watermarks(
+    admin_client: Arc<AdminClient<DefaultClientContext>>,
+    semaphore: Arc<Semaphore>,
+    mut tps: TopicPartitionsStatus,
+) -> TopicPartitionsStatus {
+    let lookups = tps.partitions.into_iter().map(|ps| {
+        let admin_client = admin_client.clone();
+        let semaphore = semaphore.clone();
+        let topic_name = tps.name.clone();
+
+        async move {
+            let _permit = semaphore.acquire_owned().await.expect("Watermark fetch semaphore closed");
+
+            tokio::task::spawn_blocking(move || fetch_partition_watermarks(&admin_client, &topic_name, ps))
+                .await
+                .unwrap_or_else(|e| panic!("Watermark fetch task panicked: {e}"))
+        }
+    });
+
+    tps.partitions = join_all(lookups).await;
+
+    tps
+}
+
+/// Fetch the begin/end offset watermarks for a single partition, and fold them into `ps`.
+///
+/// Runs on a blocking thread: called only via [`tokio::task::spawn_blocking`].
+fn fetch_partition_watermarks(
+    admin_client: &AdminClient<DefaultClientContext>,
+    topic_name: &str,
+    mut ps: PartitionStatus,
+) -> PartitionStatus {
+    match admin_client.inner().fetch_watermarks(topic_name, ps.id as i32, METADATA_FETCH_TIMEOUT) {
+        Ok((b, e)) => {
+            ps.begin_offset = b as u64;
+            ps.end_offset = e as u64;
+        },
+        Err(e) => error!("Failed to fetch begin/end watermarks for '{}:{}': {e}", topic_name, ps.id),
+    }
+
+    ps
+}
+
 impl Emitter for ClusterStatusEmitter {
     type Emitted = ClusterStatus;
 
@@ -63,8 +121,9 @@ impl Emitter for ClusterStatusEmitter {
     /// * `shutdown_rx`: A [`broadcast::Receiver`] to request the internal async task to shutdown.
     ///
     fn spawn(&self, mut shutdown_rx: broadcast::Receiver<()>) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
-        let admin_client: AdminClient<DefaultClientContext> =
-            self.admin_client_config.create().expect("Failed to allocate Admin Client");
+        let admin_client: Arc<AdminClient<DefaultClientContext>> =
+            Arc::new(self.admin_client_config.create().expect("Failed to allocate Admin Client"));
+        let watermark_semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_WATERMARK_FETCHES));
 
         let (sx, rx) = mpsc::channel::<ClusterStatus>(CHANNEL_SIZE);
 
@@ -74,38 +133,29 @@ impl Emitter for ClusterStatusEmitter {
             loop {
                 match admin_client.inner().fetch_metadata(None, METADATA_FETCH_TIMEOUT) {
                     Ok(m) => {
-                        // NOTE: Turn metadata into our `Send`-able type
+                        let fetch_start = Instant::now();
+
+                        // NOTE: Turn metadata into our `Send`-able type, fetching every topic's
+                        // partition watermarks concurrently (bounded by `watermark_semaphore`)
+                        // rather than one-by-one.
+                        let topics = join_all(m.topics().iter().map(|t| {
+                            fetch_topic_watermarks(admin_client.clone(), watermark_semaphore.clone(), TopicPartitionsStatus::from(t))
+                        }))
+                        .await;
+
                         let status = ClusterStatus {
-                            topics: m
-                                .topics()
-                                .iter()
-                                .map(|t| {
-                                    let mut tps = TopicPartitionsStatus::from(t);
-
-                                    // For each `PartitionStatus`, look up the begin/end offset watermarks
-                                    for mut ps in &mut tps.partitions {
-                                        match admin_client.inner().fetch_watermarks(
-                                            tps.name.as_str(),
-                                            ps.id as i32,
-                                            METADATA_FETCH_TIMEOUT,
-                                        ) {
-                                            Ok((b, e)) => {
-                                                // Update specific partition status with the fetched watermarks
-                                                ps.begin_offset = b as u64;
-                                                ps.end_offset = e as u64;
-                                            },
-                                            Err(e) => {
-                                                error!("Failed to fetch being/end watermarks for '{}:{}': {e}", tps.name, ps.id)
-                                            },
-                                        }
-                                    }
-
-                                    tps
-                                })
-                                .collect(),
+                            topics,
                             brokers: m.brokers().iter().map(Broker::from).collect(),
                         };
 
+                        let fetch_elapsed = fetch_start.elapsed();
+                        if fetch_elapsed > METADATA_FETCH_INTERVAL {
+                            warn!(
+                                "Metadata fetch cycle took {fetch_elapsed:?}, longer than the {METADATA_FETCH_INTERVAL:?} interval: \
+                                 the register is being starved of fresh updates"
+                            );
+                        }
+
                         let ch_cap = sx.capacity();
                         if ch_cap == 0 {
                             warn!("Emitting channel saturated: receiver too slow?");
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use rdkafka::{
+    consumer::{BaseConsumer, Consumer},
+    error::KafkaResult,
+    ClientConfig, TopicPartitionList,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+    time,
+};
+
+use crate::internals::Emitter;
+use crate::kafka_types::TopicPartition;
+
+const CHANNEL_SIZE: usize = 1;
+const CHANNEL_SEND_TIMEOUT: Duration = Duration::from_millis(100);
+
+const METADATA_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const COMMITTED_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const COMMITTED_FETCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Emits [`GroupOffsets`] via a provided [`mpsc::channel`].
+///
+/// It wraps a Consumer with `group.id` set to the Consumer Group being watched, and regularly
+/// asks it for that group's committed offsets, then emits them as [`GroupOffsets`].
+///
+/// `committed_offsets` is a metadata-only read: it neither requires this process to actually
+/// join `group_id`, nor to hold an assignment or have polled. It does, however, only report on
+/// the Topic Partitions it's explicitly asked about, and there's no API to ask the broker which
+/// ones `group_id` actually consumes without joining it. So every cycle first lists every Topic
+/// Partition currently in the cluster via `fetch_metadata`, then asks `committed_offsets` about
+/// all of them, dropping the ones with no committed offset from the result.
+///
+/// It shuts down by sending a unit via a provided [`broadcast`].
+pub struct ConsumerGroupOffsetsEmitter {
+    consumer_client_config: ClientConfig,
+    group_id: String,
+}
+
+/// This is a `Send`-able struct to carry a Consumer Group's committed offsets across thread boundaries.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GroupOffsets {
+    /// The `group.id` these offsets were committed by.
+    pub group_id: String,
+
+    /// A vector of [`PartitionCommittedOffset`], one per committed [`TopicPartition`].
+    pub offsets: Vec<PartitionCommittedOffset>,
+}
+
+/// The committed offset of a single [`TopicPartition`], as observed at `read_datetime`.
+///
+/// `read_datetime` is when *this process* read the committed offset, not when it was actually
+/// committed: the Consumer APIs used here don't expose a real commit timestamp. Treat any time
+/// lag derived from it as an approximation, not a precise "time since commit".
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PartitionCommittedOffset {
+    /// Which Topic Partition this committed offset belongs to.
+    pub topic_partition: TopicPartition,
+
+    /// The offset last committed by the Consumer Group for `topic_partition`.
+    pub offset: u64,
+
+    /// When this committed offset was read from the cluster (not when it was committed).
+    pub read_datetime: chrono::DateTime<Utc>,
+}
+
+impl ConsumerGroupOffsetsEmitter {
+    /// Create a new [`Self`], watching `group_id`'s committed offsets.
+    ///
+    /// `client_config` is taken by value and has its `group.id` set to `group_id`: the caller only needs
+    /// to supply connection settings (`bootstrap.servers` etc.), not the group itself.
+    pub fn new(mut client_config: ClientConfig, group_id: String) -> ConsumerGroupOffsetsEmitter {
+        client_config.set("group.id", &group_id);
+
+        ConsumerGroupOffsetsEmitter {
+            consumer_client_config: client_config,
+            group_id,
+        }
+    }
+}
+
+/// Fetch `group_id`'s committed offsets for every Topic Partition currently in the cluster.
+///
+/// Runs on `consumer`'s calling thread: both `fetch_metadata` and `committed_offsets` are
+/// blocking calls.
+fn fetch_group_offsets(consumer: &BaseConsumer, group_id: &str) -> KafkaResult<GroupOffsets> {
+    let metadata = consumer.fetch_metadata(None, METADATA_FETCH_TIMEOUT)?;
+
+    let mut request = TopicPartitionList::new();
+    for topic in metadata.topics() {
+        for partition in topic.partitions() {
+            request.add_partition(topic.name(), partition.id());
+        }
+    }
+
+    let committed = consumer.committed_offsets(request, COMMITTED_FETCH_TIMEOUT)?;
+    let read_datetime = Utc::now();
+
+    // NOTE: Turn the committed `TopicPartitionList` into our `Send`-able type, skipping
+    // partitions that have no committed offset yet.
+    let offsets = committed
+        .elements()
+        .iter()
+        .filter_map(|el| match el.offset().to_raw() {
+            Some(offset) if offset >= 0 => Some(PartitionCommittedOffset {
+                topic_partition: TopicPartition {
+                    topic: el.topic().to_string(),
+                    partition: el.partition(),
+                },
+                offset: offset as u64,
+                read_datetime,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Ok(GroupOffsets {
+        group_id: group_id.to_string(),
+        offsets,
+    })
+}
+
+impl Emitter for ConsumerGroupOffsetsEmitter {
+    type Emitted = GroupOffsets;
+
+    /// Spawn a new async task to run the business logic of this struct.
+    ///
+    /// When this emitter gets spawned, it returns a [`mpsc::Receiver`] for [`GroupOffsets`],
+    /// and a [`JoinHandle`] to help join on the task spawned internally.
+    /// The task concludes (joins) only ones the inner task of the emitter terminates.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown_rx`: A [`broadcast::Receiver`] to request the internal async task to shutdown.
+    ///
+    fn spawn(&self, mut shutdown_rx: broadcast::Receiver<()>) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
+        let consumer: BaseConsumer = self.consumer_client_config.create().expect("Failed to allocate Consumer");
+
+        let group_id = self.group_id.clone();
+
+        let (sx, rx) = mpsc::channel::<GroupOffsets>(CHANNEL_SIZE);
+
+        let join_handle = tokio::spawn(async move {
+            let mut interval = time::interval(COMMITTED_FETCH_INTERVAL);
+
+            loop {
+                match fetch_group_offsets(&consumer, &group_id) {
+                    Ok(status) => {
+                        let ch_cap = sx.capacity();
+                        if ch_cap == 0 {
+                            warn!("Emitting channel saturated: receiver too slow?");
+                        }
+
+                        tokio::select! {
+                            // Send the latest `GroupOffsets`
+                            res = sx.send_timeout(status, CHANNEL_SEND_TIMEOUT) => {
+                                if let Err(e) = res {
+                                    error!("Failed to emit group offsets: {e}");
+                                }
+                            },
+
+                            // Initiate shutdown: by letting this task conclude,
+                            // the receiver of `GroupOffsets` will detect the channel is closing
+                            // on the sender end, and conclude its own activity/task.
+                            _ = shutdown_rx.recv() => {
+                                info!("Received shutdown signal");
+                                break;
+                            },
+                        }
+                    },
+                    Err(e) => error!("Failed to fetch committed offsets for group '{group_id}': {e}"),
+                }
+
+                interval.tick().await;
+            }
+        });
+
+        (rx, join_handle)
+    }
+}
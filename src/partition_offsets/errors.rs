@@ -0,0 +1,48 @@
+use thiserror::Error;
+
+/// Convenience alias for `Result<T, PartitionOffsetsError>`.
+pub type PartitionOffsetsResult<T> = Result<T, PartitionOffsetsError>;
+
+/// Errors that can occur while tracking or estimating Partition offset/time lag.
+#[derive(Debug, Error)]
+pub enum PartitionOffsetsError {
+    /// No [`super::lag_estimator::PartitionLagEstimator`] is registered yet for this `(topic, partition)`.
+    #[error("No lag estimator found for Topic Partition '{0}:{1}'")]
+    LagEstimatorNotFound(String, i32),
+
+    /// No committed offsets have been observed yet for this Consumer Group.
+    #[error("No committed offsets found for Consumer Group '{0}'")]
+    GroupOffsetsNotFound(String),
+
+    /// Too few (or too few *distinct*) history samples to estimate anything from.
+    #[error("Not enough history to estimate lag: need at least 2 distinct samples")]
+    NotEnoughHistory,
+
+    /// `consumed_offset` isn't bracketed by any two samples in the retained history window
+    /// (e.g. it's older than the oldest retained sample), so interpolation can't answer.
+    #[error("Consumed offset falls outside the retained history window")]
+    ConsumedOffsetOutOfHistory,
+
+    /// The estimated production rate over the history window is zero or negative,
+    /// so a regression-based time lag estimate cannot be solved.
+    #[error("Cannot regress lag: estimated production rate is zero or negative")]
+    NonPositiveProductionRate,
+
+    /// Failed to (de)serialize an offsets snapshot.
+    #[error("Failed to (de)serialize offsets snapshot: {0}")]
+    SnapshotSerde(#[from] serde_json::Error),
+
+    /// Failed to read/write an offsets snapshot from/to its backend.
+    #[error("Failed to read/write offsets snapshot: {0}")]
+    SnapshotIo(#[from] std::io::Error),
+
+    /// A broker-backed lag estimate failed because the underlying Kafka client call failed.
+    #[error("Kafka client error while resolving a broker-backed lag estimate: {0}")]
+    Kafka(#[from] rdkafka::error::KafkaError),
+
+    /// The broker couldn't resolve an offset for the probed timestamp on this Topic Partition
+    /// (`offsets_for_times` returned the `Offset::Invalid` sentinel), e.g. because the probe
+    /// landed at or after the last produced message.
+    #[error("Broker couldn't resolve an offset for the probed timestamp on Topic Partition '{0}:{1}'")]
+    BrokerOffsetUnresolved(String, i32),
+}
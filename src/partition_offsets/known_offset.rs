@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+
+/// A single known `(offset, UTC timestamp)` sample for a Topic Partition.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KnownOffset {
+    /// The offset this sample was taken at.
+    pub offset: u64,
+
+    /// When `offset` was observed to be the log head.
+    pub datetime: DateTime<Utc>,
+}
@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::errors::{PartitionOffsetsError, PartitionOffsetsResult};
+use super::known_offset::KnownOffset;
+
+/// How [`PartitionLagEstimator::estimate_time_lag`] turns a `consumed_offset` into a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagEstimationMode {
+    /// Interpolate linearly between the two history samples bracketing `consumed_offset`.
+    ///
+    /// Cheap and exact when production is steady, but jumpy when it's bursty, and unusable
+    /// when `consumed_offset` falls outside the bracketed range.
+    Interpolation,
+
+    /// Fit an ordinary least-squares line over the whole history window and solve for the
+    /// timestamp directly, instead of only looking at the two nearest points.
+    ///
+    /// Smooths out bursty producers, at the cost of being a window-wide average rather than
+    /// a local estimate. Falls back to [`Self::Interpolation`] when the fit isn't usable
+    /// (fewer than two distinct points, or a non-positive production rate).
+    Regression,
+}
+
+/// Tracks a bounded history of `(offset, UTC timestamp)` samples for a single Topic Partition,
+/// and estimates a consumer's offset/time lag from it.
+pub struct PartitionLagEstimator {
+    history: VecDeque<(u64, DateTime<Utc>)>,
+    capacity: usize,
+    mode: LagEstimationMode,
+}
+
+impl PartitionLagEstimator {
+    /// Create a new, empty [`Self`] holding up to `capacity` `(offset, UTC TS)` samples,
+    /// estimating time lag via [`LagEstimationMode::Interpolation`].
+    pub fn new(capacity: usize) -> Self {
+        Self::with_mode(capacity, LagEstimationMode::Interpolation)
+    }
+
+    /// Create a new, empty [`Self`] using the given [`LagEstimationMode`].
+    pub fn with_mode(capacity: usize, mode: LagEstimationMode) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            mode,
+        }
+    }
+
+    /// Record a new `(offset, UTC TS)` sample, evicting the oldest one if the history is full.
+    pub fn update(&mut self, offset: u64, datetime: DateTime<Utc>) {
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back((offset, datetime));
+    }
+
+    /// The full `(offset, UTC TS)` history, oldest first, e.g. for snapshotting.
+    pub fn history(&self) -> Vec<(u64, DateTime<Utc>)> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// The earliest known `(offset, UTC TS)` sample still held.
+    pub fn earliest_offset(&self) -> Option<KnownOffset> {
+        self.history.front().map(|&(offset, datetime)| KnownOffset { offset, datetime })
+    }
+
+    /// The most recent known `(offset, UTC TS)` sample.
+    pub fn latest_offset(&self) -> Option<KnownOffset> {
+        self.history.back().map(|&(offset, datetime)| KnownOffset { offset, datetime })
+    }
+
+    /// How full the history is, as a percentage of `capacity`.
+    pub fn usage_percent(&self) -> f64 {
+        self.history.len() as f64 / self.capacity as f64 * 100_f64
+    }
+
+    /// Estimate offset lag for a consumer at `consumed_offset`, i.e. how many offsets behind
+    /// the log head it is.
+    pub fn estimate_offset_lag(&self, consumed_offset: u64) -> PartitionOffsetsResult<u64> {
+        let latest = self.latest_offset().ok_or(PartitionOffsetsError::NotEnoughHistory)?;
+
+        Ok(latest.offset.saturating_sub(consumed_offset))
+    }
+
+    /// Estimate time lag for a consumer at `consumed_offset`, committed at `consumed_offset_datetime`.
+    ///
+    /// Uses `self.mode` to pick how the wall-clock time of `consumed_offset` is estimated,
+    /// falling back to interpolation when regression isn't usable (see [`LagEstimationMode`]).
+    pub fn estimate_time_lag(
+        &self,
+        consumed_offset: u64,
+        consumed_offset_datetime: DateTime<Utc>,
+    ) -> PartitionOffsetsResult<Duration> {
+        if let LagEstimationMode::Regression = self.mode {
+            match self.estimate_time_lag_regression(consumed_offset) {
+                Ok(lag) => return Ok(lag),
+                Err(e) => trace!("Falling back to interpolation for time lag estimation: {e}"),
+            }
+        }
+
+        self.estimate_time_lag_interpolation(consumed_offset, consumed_offset_datetime)
+    }
+
+    /// Interpolate linearly between the two history samples bracketing `consumed_offset`.
+    ///
+    /// Returns [`PartitionOffsetsError::ConsumedOffsetOutOfHistory`] when `consumed_offset`
+    /// isn't bracketed by any two samples in history (e.g. it's older than the retained window),
+    /// rather than a rough guess: callers that can, like
+    /// [`super::register::PartitionOffsetsRegister::estimate_time_lag_broker_backed`], use this
+    /// to know they should ask the broker instead.
+    fn estimate_time_lag_interpolation(
+        &self,
+        consumed_offset: u64,
+        _consumed_offset_datetime: DateTime<Utc>,
+    ) -> PartitionOffsetsResult<Duration> {
+        let latest = self.latest_offset().ok_or(PartitionOffsetsError::NotEnoughHistory)?;
+
+        for window in self.history.iter().collect::<Vec<_>>().windows(2) {
+            let &(lo_offset, lo_ts) = window[0];
+            let &(hi_offset, hi_ts) = window[1];
+
+            if hi_offset <= lo_offset || consumed_offset < lo_offset || consumed_offset > hi_offset {
+                continue;
+            }
+
+            let offset_span = (hi_offset - lo_offset) as f64;
+            let time_span_ms = (hi_ts - lo_ts).num_milliseconds() as f64;
+            let frac = (consumed_offset - lo_offset) as f64 / offset_span;
+            let estimated_ts = lo_ts + Duration::milliseconds((frac * time_span_ms) as i64);
+
+            return Ok(latest.datetime - estimated_ts);
+        }
+
+        Err(PartitionOffsetsError::ConsumedOffsetOutOfHistory)
+    }
+
+    /// Fit an ordinary least-squares line over the whole history window and solve for the
+    /// timestamp at which `consumed_offset` was the log head.
+    ///
+    /// Treats each stored entry as a point `(t_i, y_i)`, with `t_i` the seconds elapsed since
+    /// the earliest sample and `y_i` its offset: `b = Σ((t_i−t̄)(y_i−ȳ)) / Σ((t_i−t̄)²)` is the
+    /// estimated production rate (offsets/sec) and `a = ȳ − b·t̄` the intercept. Solving
+    /// `consumed_offset = a + b·t` for `t` gives the estimated wall-clock offset of the sample.
+    fn estimate_time_lag_regression(&self, consumed_offset: u64) -> PartitionOffsetsResult<Duration> {
+        let latest = self.latest_offset().ok_or(PartitionOffsetsError::NotEnoughHistory)?;
+        let first_ts = self.history.front().map(|&(_, ts)| ts).ok_or(PartitionOffsetsError::NotEnoughHistory)?;
+
+        let points: Vec<(f64, f64)> = self
+            .history
+            .iter()
+            .map(|&(offset, ts)| ((ts - first_ts).num_milliseconds() as f64 / 1000_f64, offset as f64))
+            .collect();
+
+        if points.len() < 2 || points.iter().all(|p| p.0 == points[0].0) {
+            return Err(PartitionOffsetsError::NotEnoughHistory);
+        }
+
+        let n = points.len() as f64;
+        let t_mean = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let y_mean = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+        let (num, den) = points.iter().fold((0_f64, 0_f64), |(num, den), &(t, y)| {
+            let dt = t - t_mean;
+            (num + dt * (y - y_mean), den + dt * dt)
+        });
+
+        if den == 0_f64 {
+            return Err(PartitionOffsetsError::NotEnoughHistory);
+        }
+
+        let b = num / den;
+        if b <= 0_f64 {
+            return Err(PartitionOffsetsError::NonPositiveProductionRate);
+        }
+
+        let a = y_mean - b * t_mean;
+        let t_for_consumed_offset = (consumed_offset as f64 - a) / b;
+        let estimated_ts = first_ts + Duration::milliseconds((t_for_consumed_offset * 1000_f64) as i64);
+
+        Ok(latest.datetime - estimated_ts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn regression_solves_a_steady_production_rate() {
+        let mut estimator = PartitionLagEstimator::with_mode(10, LagEstimationMode::Regression);
+        for i in 0..5_i64 {
+            estimator.update(1_000 + (i as u64) * 100, ts(i));
+        }
+
+        // Latest sample is (1_400, t=4s). Offset 1_200 was the head at t=2s, so time lag is 2s.
+        let lag = estimator.estimate_time_lag(1_200, ts(2)).unwrap();
+        assert_eq!(lag, Duration::seconds(2));
+    }
+
+    #[test]
+    fn regression_returns_non_positive_production_rate_when_offsets_dont_increase() {
+        let mut estimator = PartitionLagEstimator::with_mode(10, LagEstimationMode::Regression);
+        estimator.update(1_000, ts(0));
+        estimator.update(1_000, ts(1));
+        estimator.update(1_000, ts(2));
+
+        let err = estimator.estimate_time_lag_regression(1_000).unwrap_err();
+        assert!(matches!(err, PartitionOffsetsError::NonPositiveProductionRate));
+    }
+
+    #[test]
+    fn regression_returns_not_enough_history_with_a_single_distinct_timestamp() {
+        let mut estimator = PartitionLagEstimator::with_mode(10, LagEstimationMode::Regression);
+        estimator.update(1_000, ts(5));
+        estimator.update(1_100, ts(5));
+
+        let err = estimator.estimate_time_lag_regression(1_050).unwrap_err();
+        assert!(matches!(err, PartitionOffsetsError::NotEnoughHistory));
+    }
+
+    #[test]
+    fn regression_falls_back_to_interpolation_when_the_fit_is_unusable() {
+        let mut estimator = PartitionLagEstimator::with_mode(10, LagEstimationMode::Regression);
+        estimator.update(1_000, ts(0));
+        estimator.update(2_000, ts(10));
+        estimator.update(1_000, ts(20)); // Back down to the first offset: makes the overall regression's rate 0.
+
+        // Regression can't solve this (non-positive rate), so `estimate_time_lag` should fall
+        // back to interpolation, which *can* bracket 1_500 between the first two samples.
+        let lag = estimator.estimate_time_lag(1_500, ts(0)).unwrap();
+        assert_eq!(lag, Duration::seconds(15));
+    }
+
+    #[test]
+    fn interpolation_estimates_between_two_bracketing_samples() {
+        let mut estimator = PartitionLagEstimator::new(10);
+        estimator.update(1_000, ts(0));
+        estimator.update(2_000, ts(10));
+
+        let lag = estimator.estimate_time_lag(1_500, ts(0)).unwrap();
+        assert_eq!(lag, Duration::seconds(5));
+    }
+
+    #[test]
+    fn interpolation_errors_when_consumed_offset_is_outside_the_window() {
+        let mut estimator = PartitionLagEstimator::new(10);
+        estimator.update(1_000, ts(0));
+        estimator.update(2_000, ts(10));
+
+        let err = estimator.estimate_time_lag(500, ts(0)).unwrap_err();
+        assert!(matches!(err, PartitionOffsetsError::ConsumedOffsetOutOfHistory));
+    }
+
+    #[test]
+    fn offset_lag_needs_at_least_one_sample() {
+        let estimator = PartitionLagEstimator::new(10);
+        let err = estimator.estimate_offset_lag(0).unwrap_err();
+        assert!(matches!(err, PartitionOffsetsError::NotEnoughHistory));
+    }
+}
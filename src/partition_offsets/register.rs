@@ -1,21 +1,75 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration as StdDuration,
+};
 
 use chrono::{DateTime, Duration, Utc};
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{Offset, TopicPartitionList};
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::RwLock;
+use tokio::time;
 
 use super::emitter::PartitionOffset;
 use super::errors::{PartitionOffsetsError, PartitionOffsetsResult};
-use super::lag_estimator::PartitionLagEstimator;
+use super::lag_estimator::{LagEstimationMode, PartitionLagEstimator};
+use super::snapshot::PartitionOffsetsSnapshotter;
 
+use crate::cluster_status_emitter::ClusterStatus;
+use crate::consumer_group_offsets_emitter::GroupOffsets;
 use crate::kafka_types::TopicPartition;
 use crate::partition_offsets::known_offset::KnownOffset;
 
+/// Per-[`TopicPartition`] lag of a single Consumer Group, as returned by
+/// [`PartitionOffsetsRegister::estimate_group_lag`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GroupPartitionLag {
+    /// Which Topic Partition this lag was estimated for.
+    pub topic_partition: TopicPartition,
+
+    /// How many offsets behind the log head the group's committed offset is.
+    pub offset_lag: u64,
+
+    /// How far behind in time the group's committed offset is.
+    pub time_lag: Duration,
+}
+
+/// Where a [`TimeLagEstimate`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagEstimateSource {
+    /// Computed entirely from the register's own in-memory history: cheap, no cluster round-trip.
+    LocalHistory,
+
+    /// The register's history couldn't answer, so the estimate required a live broker query
+    /// (bisecting over time with `offsets_for_times`): more accurate for offsets outside the
+    /// retained history window, but costs a round-trip to the cluster.
+    BrokerQuery,
+}
+
+/// A time lag estimate tagged with where it came from, so callers can weigh its accuracy/cost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeLagEstimate {
+    /// The estimated time lag.
+    pub time_lag: Duration,
+
+    /// Whether `time_lag` came from local history or a live broker query.
+    pub source: LagEstimateSource,
+}
+
 /// Holds the offset of all Topic Partitions in the Kafka Cluster, and can estimate lag of Consumers.
 ///
 /// This is where a known Consumer Group, at a known offset in time, can get it's lag estimated.
+///
+/// Its history is periodically persisted via a [`PartitionOffsetsSnapshotter`], and hydrated
+/// from the latest snapshot on construction, so estimates stay usable across restarts instead
+/// of having to re-warm from scratch.
 pub struct PartitionOffsetsRegister {
     estimators: Arc<RwLock<HashMap<TopicPartition, RwLock<PartitionLagEstimator>>>>,
+    group_offsets: Arc<RwLock<HashMap<String, GroupOffsets>>>,
+    offsets_history: usize,
+    lag_estimation_mode: LagEstimationMode,
+    broker_handle: Option<Arc<BaseConsumer>>,
 }
 
 impl PartitionOffsetsRegister {
@@ -28,14 +82,64 @@ impl PartitionOffsetsRegister {
     ///   History for each (`Topic, Partition`) pair is kept in a queue-like structure of this
     ///   size. Each entry in the structure is the pair (`Offset, UTC TS`): each pair represents
     ///   at what moment in time that particular offset was valid.
-    pub fn new(mut rx: Receiver<PartitionOffset>, offsets_history: usize) -> Self {
+    /// * `snapshotter` - Backend used to persist and restore the register's history across
+    ///   restarts. See [`PartitionOffsetsSnapshotter`].
+    /// * `snapshot_interval` - How often the register's current state is saved via `snapshotter`.
+    /// * `group_offsets_rx` - Channel [`Receiver`] for [`GroupOffsets`], as produced by a
+    ///   `ConsumerGroupOffsetsEmitter`. Feeds [`Self::estimate_group_lag`].
+    /// * `lag_estimation_mode` - Whether per-partition time lag is estimated via interpolation
+    ///   or regression. See [`LagEstimationMode`].
+    /// * `cluster_status_rx` - Channel [`Receiver`] for [`ClusterStatus`], as produced by a
+    ///   `ClusterStatusEmitter`. Drives [`Self::reconcile`] so estimators for deleted/reassigned
+    ///   partitions don't leak forever, and new ones are ready as soon as they're discovered.
+    /// * `broker_handle` - Optional Consumer handle used by [`Self::estimate_time_lag_broker_backed`]
+    ///   to fall back to a live broker query when local history can't answer. Pass `None` to
+    ///   disable the broker-backed path entirely.
+    pub fn new(
+        mut rx: Receiver<PartitionOffset>,
+        offsets_history: usize,
+        snapshotter: Arc<dyn PartitionOffsetsSnapshotter>,
+        snapshot_interval: StdDuration,
+        mut group_offsets_rx: Receiver<GroupOffsets>,
+        lag_estimation_mode: LagEstimationMode,
+        mut cluster_status_rx: Receiver<ClusterStatus>,
+        broker_handle: Option<Arc<BaseConsumer>>,
+    ) -> Self {
+        // Hydrate from the latest snapshot, if any, before the register (and its update loop)
+        // exist: this is what lets the register be immediately useful after a restart, instead
+        // of having to re-warm every estimator from live updates again. Built up as a plain local
+        // map first, since `new()` runs in async context (it calls `tokio::spawn` below) and
+        // `RwLock::blocking_write` would panic if used here.
+        let mut hydrated_estimators = HashMap::new();
+        match snapshotter.load() {
+            Ok(snapshot) => {
+                if !snapshot.is_empty() {
+                    debug!("Hydrating {} estimator(s) from snapshot", snapshot.len());
+                }
+
+                for (tp, history) in snapshot {
+                    let mut estimator = PartitionLagEstimator::with_mode(offsets_history, lag_estimation_mode);
+                    for (offset, ts) in history {
+                        estimator.update(offset, ts);
+                    }
+                    hydrated_estimators.insert(tp, RwLock::new(estimator));
+                }
+            },
+            Err(e) => error!("Failed to load offsets snapshot, starting cold: {e}"),
+        }
+
         let por = Self {
-            estimators: Arc::new(RwLock::new(HashMap::new())),
+            estimators: Arc::new(RwLock::new(hydrated_estimators)),
+            group_offsets: Arc::new(RwLock::new(HashMap::new())),
+            offsets_history,
+            lag_estimation_mode,
+            broker_handle,
         };
 
         // A clone of the `por.estimator` will be moved into the async task
         // that updates the register.
         let estimators_clone = por.estimators.clone();
+        let group_offsets_clone = por.group_offsets.clone();
 
         // The Register is essentially "self updating" its data, by listening
         // on a channel for updates.
@@ -46,6 +150,8 @@ impl PartitionOffsetsRegister {
         tokio::spawn(async move {
             debug!("Begin receiving PartitionOffset updates");
 
+            let mut snapshot_interval_tick = time::interval(snapshot_interval);
+
             loop {
                 tokio::select! {
                     Some(po) = rx.recv() => {
@@ -59,8 +165,9 @@ impl PartitionOffsetsRegister {
                         if !w_guard.contains_key(&k) {
                             w_guard.insert(
                                 k.clone(),
-                                RwLock::new(PartitionLagEstimator::new(
+                                RwLock::new(PartitionLagEstimator::with_mode(
                                     offsets_history,
+                                    lag_estimation_mode,
                                 )),
                             );
                         }
@@ -75,6 +182,38 @@ impl PartitionOffsetsRegister {
                             .await
                             .update(po.latest_offset, po.read_datetime);
                     },
+
+                    Some(go) = group_offsets_rx.recv() => {
+                        trace!("Updating committed offsets for group: {}", go.group_id);
+                        group_offsets_clone.write().await.insert(go.group_id.clone(), go);
+                    },
+
+                    Some(cs) = cluster_status_rx.recv() => {
+                        let live: HashSet<TopicPartition> = cs
+                            .topics
+                            .iter()
+                            .flat_map(|t| {
+                                t.partitions.iter().map(|p| TopicPartition {
+                                    topic: t.name.clone(),
+                                    partition: p.id as i32,
+                                })
+                            })
+                            .collect();
+
+                        Self::reconcile_estimators(&estimators_clone, &live, offsets_history, lag_estimation_mode).await;
+                    },
+
+                    _ = snapshot_interval_tick.tick() => {
+                        let mut snapshot = HashMap::new();
+                        for (tp, est) in estimators_clone.read().await.iter() {
+                            snapshot.insert(tp.clone(), est.read().await.history());
+                        }
+
+                        if let Err(e) = snapshotter.save(&snapshot) {
+                            error!("Failed to save offsets snapshot: {e}");
+                        }
+                    },
+
                     else => {
                         info!("Emitters stopping: breaking (internal) loop");
                         break;
@@ -138,6 +277,113 @@ impl PartitionOffsetsRegister {
             .estimate_time_lag(consumed_offset, consumed_offset_datetime)
     }
 
+    /// Like [`Self::estimate_time_lag`], but falls back to a live broker query when local
+    /// history can't answer (no estimator yet, or `consumed_offset` falls outside the retained
+    /// history window).
+    ///
+    /// Requires a `broker_handle` to have been passed to [`Self::new`]; without one, this
+    /// behaves exactly like [`Self::estimate_time_lag`], always reporting [`LagEstimateSource::LocalHistory`].
+    ///
+    /// # Arguments
+    ///
+    /// * `topic_partition` - Topic Partition consumed by the Consumer
+    /// * `consumed_offset` - Offset up to which the Consumer has consumed
+    /// * `consumed_offset_datetime` - [`Datetime<Utc>`] when the `consumed_offset` was committed
+    pub async fn estimate_time_lag_broker_backed(
+        &self,
+        topic_partition: &TopicPartition,
+        consumed_offset: u64,
+        consumed_offset_datetime: DateTime<Utc>,
+    ) -> PartitionOffsetsResult<TimeLagEstimate> {
+        match self.estimate_time_lag(topic_partition, consumed_offset, consumed_offset_datetime).await {
+            Ok(time_lag) => Ok(TimeLagEstimate {
+                time_lag,
+                source: LagEstimateSource::LocalHistory,
+            }),
+            Err(local_err) => {
+                let Some(broker_handle) = self.broker_handle.clone() else {
+                    return Err(local_err);
+                };
+
+                debug!("Local history couldn't estimate time lag for {topic_partition:?} ({local_err}), falling back to broker query");
+
+                let topic_partition = topic_partition.clone();
+                tokio::task::spawn_blocking(move || Self::query_broker_time_lag(&broker_handle, &topic_partition, consumed_offset))
+                    .await
+                    .unwrap_or_else(|e| panic!("Broker time lag query task panicked: {e}"))
+            },
+        }
+    }
+
+    /// Resolve the offset the broker considers valid at `timestamp`, via `offsets_for_times`.
+    ///
+    /// Runs on a blocking thread: called only via [`tokio::task::spawn_blocking`].
+    fn resolve_offset_for_timestamp(
+        broker_handle: &BaseConsumer,
+        topic_partition: &TopicPartition,
+        timestamp: DateTime<Utc>,
+    ) -> PartitionOffsetsResult<i64> {
+        let mut request = TopicPartitionList::new();
+        request.add_partition_offset(
+            &topic_partition.topic,
+            topic_partition.partition,
+            Offset::Offset(timestamp.timestamp_millis()),
+        )?;
+
+        let resolved = broker_handle.offsets_for_times(request, StdDuration::from_secs(10))?;
+
+        let el = resolved
+            .find_partition(&topic_partition.topic, topic_partition.partition)
+            .ok_or(PartitionOffsetsError::NotEnoughHistory)?;
+
+        // `offsets_for_times` sets `Offset::Invalid` (librdkafka's `RD_KAFKA_OFFSET_INVALID`
+        // sentinel) rather than leaving the element out when it can't resolve an offset for
+        // `timestamp` (e.g. probing at/after the last produced message). `Offset::Invalid.to_raw()`
+        // still returns `Some(-1001)`, so this has to be checked explicitly before trusting the
+        // value as a real offset; otherwise callers silently bisect against a bogus offset.
+        if el.offset() == Offset::Invalid {
+            return Err(PartitionOffsetsError::BrokerOffsetUnresolved(
+                topic_partition.topic.clone(),
+                topic_partition.partition,
+            ));
+        }
+
+        el.offset().to_raw().ok_or(PartitionOffsetsError::NotEnoughHistory)
+    }
+
+    /// Find the wall-clock time at which `consumed_offset` was the log head, by bisecting
+    /// between 7 days ago and now and repeatedly resolving the midpoint via
+    /// [`Self::resolve_offset_for_timestamp`] ("vice versa" of `offsets_for_timestamp`, since
+    /// rdkafka only exposes the timestamp-to-offset direction).
+    ///
+    /// Runs on a blocking thread: called only via [`tokio::task::spawn_blocking`].
+    fn query_broker_time_lag(
+        broker_handle: &BaseConsumer,
+        topic_partition: &TopicPartition,
+        consumed_offset: u64,
+    ) -> PartitionOffsetsResult<TimeLagEstimate> {
+        let now = Utc::now();
+        let mut lo = now - Duration::days(7);
+        let mut hi = now;
+
+        const MAX_BISECTIONS: u8 = 20;
+        for _ in 0..MAX_BISECTIONS {
+            let mid = lo + (hi - lo) / 2;
+            let offset_at_mid = Self::resolve_offset_for_timestamp(broker_handle, topic_partition, mid)?;
+
+            if (offset_at_mid as u64) < consumed_offset {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        Ok(TimeLagEstimate {
+            time_lag: now - hi,
+            source: LagEstimateSource::BrokerQuery,
+        })
+    }
+
     /// Get the earliest known offset of specific [`TopicPartition`].
     ///
     /// # Arguments
@@ -178,6 +424,104 @@ impl PartitionOffsetsRegister {
             .cloned()
     }
 
+    /// Estimate offset and time lag for every [`TopicPartition`] committed by a Consumer Group,
+    /// using its most recently observed committed offsets.
+    ///
+    /// This spares callers from having to fetch and pass `consumed_offset`/`consumed_offset_datetime`
+    /// themselves: the register joins the committed offsets fed in via a `ConsumerGroupOffsetsEmitter`
+    /// against its own stored history.
+    ///
+    /// Per-partition lag here is history-derived only: `committed.read_datetime` is when this
+    /// process observed the committed offset, not a true commit timestamp (see
+    /// [`crate::consumer_group_offsets_emitter::PartitionCommittedOffset::read_datetime`]), so
+    /// treat `time_lag` as an approximation rather than "time since commit".
+    ///
+    /// A partition whose lag can't be estimated (e.g. no estimator yet, or not enough history)
+    /// is logged and skipped rather than failing the whole group: a Consumer Group can commit
+    /// to many Topic Partitions, and one of them being momentarily unavailable shouldn't hide
+    /// the lag of all the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `group_id` - The `group.id` to estimate lag for
+    pub async fn estimate_group_lag(&self, group_id: &str) -> PartitionOffsetsResult<Vec<GroupPartitionLag>> {
+        let group_offsets = self
+            .group_offsets
+            .read()
+            .await
+            .get(group_id)
+            .ok_or_else(|| PartitionOffsetsError::GroupOffsetsNotFound(group_id.to_string()))?
+            .clone();
+
+        let mut lags = Vec::with_capacity(group_offsets.offsets.len());
+
+        for committed in &group_offsets.offsets {
+            let offset_lag = match self.estimate_offset_lag(&committed.topic_partition, committed.offset).await {
+                Ok(offset_lag) => offset_lag,
+                Err(e) => {
+                    debug!("Skipping offset lag for {:?} in group '{group_id}': {e}", committed.topic_partition);
+                    continue;
+                },
+            };
+            let time_lag = match self
+                .estimate_time_lag(&committed.topic_partition, committed.offset, committed.read_datetime)
+                .await
+            {
+                Ok(time_lag) => time_lag,
+                Err(e) => {
+                    debug!("Skipping time lag for {:?} in group '{group_id}': {e}", committed.topic_partition);
+                    continue;
+                },
+            };
+
+            lags.push(GroupPartitionLag {
+                topic_partition: committed.topic_partition.clone(),
+                offset_lag,
+                time_lag,
+            });
+        }
+
+        Ok(lags)
+    }
+
+    /// Reconcile `self`'s estimators against the given set of currently live [`TopicPartition`]s.
+    ///
+    /// Drops any estimator whose `TopicPartition` is no longer present (e.g. the topic or
+    /// partition was deleted/reassigned), and pre-creates empty estimators for ones that weren't
+    /// tracked yet, so lag is available as soon as updates for them start flowing in.
+    ///
+    /// # Arguments
+    ///
+    /// * `live` - The authoritative set of [`TopicPartition`]s that currently exist in the cluster
+    pub async fn reconcile(&self, live: &HashSet<TopicPartition>) {
+        Self::reconcile_estimators(&self.estimators, live, self.offsets_history, self.lag_estimation_mode).await
+    }
+
+    async fn reconcile_estimators(
+        estimators: &Arc<RwLock<HashMap<TopicPartition, RwLock<PartitionLagEstimator>>>>,
+        live: &HashSet<TopicPartition>,
+        offsets_history: usize,
+        lag_estimation_mode: LagEstimationMode,
+    ) {
+        let mut w_guard = estimators.write().await;
+
+        let stale: Vec<TopicPartition> = w_guard.keys().filter(|tp| !live.contains(tp)).cloned().collect();
+        for tp in stale {
+            debug!("Evicting estimator for Topic Partition no longer live: {:?}", tp);
+            w_guard.remove(&tp);
+        }
+
+        for tp in live {
+            if !w_guard.contains_key(tp) {
+                debug!("Pre-creating estimator for newly discovered Topic Partition: {:?}", tp);
+                w_guard.insert(
+                    tp.clone(),
+                    RwLock::new(PartitionLagEstimator::with_mode(offsets_history, lag_estimation_mode)),
+                );
+            }
+        }
+    }
+
     /// Get some basic registry usage stats.
     ///
     /// Returns the usage of the internal [`PartitionLagEstimator`]s, as `(min, max, avg, count)` tuple.
@@ -209,3 +553,133 @@ impl PartitionOffsetsRegister {
         (min, max, sum / count as f64, count)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::consumer_group_offsets_emitter::{GroupOffsets, PartitionCommittedOffset};
+
+    use super::*;
+
+    fn tp(topic: &str, partition: i32) -> TopicPartition {
+        TopicPartition {
+            topic: topic.to_string(),
+            partition,
+        }
+    }
+
+    fn ts(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    /// Build a [`PartitionOffsetsRegister`] directly from its fields rather than via [`PartitionOffsetsRegister::new`],
+    /// so tests can seed `estimators`/`group_offsets` synchronously instead of racing the
+    /// register's background update loop.
+    fn register_with(
+        estimators: HashMap<TopicPartition, RwLock<PartitionLagEstimator>>,
+        group_offsets: HashMap<String, GroupOffsets>,
+    ) -> PartitionOffsetsRegister {
+        PartitionOffsetsRegister {
+            estimators: Arc::new(RwLock::new(estimators)),
+            group_offsets: Arc::new(RwLock::new(group_offsets)),
+            offsets_history: 10,
+            lag_estimation_mode: LagEstimationMode::Interpolation,
+            broker_handle: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn estimate_group_lag_errors_when_group_is_unknown() {
+        let register = register_with(HashMap::new(), HashMap::new());
+
+        let err = register.estimate_group_lag("missing-group").await.unwrap_err();
+        assert!(matches!(err, PartitionOffsetsError::GroupOffsetsNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn estimate_group_lag_skips_partitions_whose_lag_cant_be_estimated() {
+        let mut estimator = PartitionLagEstimator::new(10);
+        estimator.update(1_000, ts(0));
+        estimator.update(1_100, ts(10));
+
+        let mut estimators = HashMap::new();
+        estimators.insert(tp("orders", 0), RwLock::new(estimator));
+        // "orders:1" intentionally has no estimator, simulating a partition this process hasn't
+        // seen a `PartitionOffset` update for yet: its lag can't be estimated and should be
+        // skipped rather than failing the whole group.
+
+        let mut group_offsets = HashMap::new();
+        group_offsets.insert(
+            "my-group".to_string(),
+            GroupOffsets {
+                group_id: "my-group".to_string(),
+                offsets: vec![
+                    PartitionCommittedOffset {
+                        topic_partition: tp("orders", 0),
+                        offset: 1_050,
+                        read_datetime: ts(5),
+                    },
+                    PartitionCommittedOffset {
+                        topic_partition: tp("orders", 1),
+                        offset: 50,
+                        read_datetime: ts(5),
+                    },
+                ],
+            },
+        );
+
+        let register = register_with(estimators, group_offsets);
+
+        let lags = register.estimate_group_lag("my-group").await.unwrap();
+
+        assert_eq!(lags.len(), 1);
+        assert_eq!(lags[0].topic_partition, tp("orders", 0));
+        assert_eq!(lags[0].offset_lag, 50);
+    }
+
+    #[tokio::test]
+    async fn reconcile_evicts_estimators_for_partitions_no_longer_live() {
+        let mut initial = HashMap::new();
+        initial.insert(tp("orders", 0), RwLock::new(PartitionLagEstimator::new(10)));
+        initial.insert(tp("orders", 1), RwLock::new(PartitionLagEstimator::new(10)));
+        let estimators = Arc::new(RwLock::new(initial));
+
+        let live: HashSet<TopicPartition> = [tp("orders", 0)].into_iter().collect();
+
+        PartitionOffsetsRegister::reconcile_estimators(&estimators, &live, 10, LagEstimationMode::Interpolation).await;
+
+        let guard = estimators.read().await;
+        assert_eq!(guard.len(), 1);
+        assert!(guard.contains_key(&tp("orders", 0)));
+    }
+
+    #[tokio::test]
+    async fn reconcile_pre_creates_estimators_for_newly_discovered_partitions() {
+        let estimators = Arc::new(RwLock::new(HashMap::new()));
+        let live: HashSet<TopicPartition> = [tp("orders", 0), tp("orders", 1)].into_iter().collect();
+
+        PartitionOffsetsRegister::reconcile_estimators(&estimators, &live, 10, LagEstimationMode::Interpolation).await;
+
+        let guard = estimators.read().await;
+        assert_eq!(guard.len(), 2);
+        assert!(guard.contains_key(&tp("orders", 0)));
+        assert!(guard.contains_key(&tp("orders", 1)));
+    }
+
+    #[tokio::test]
+    async fn reconcile_leaves_history_of_still_live_partitions_untouched() {
+        let mut estimator = PartitionLagEstimator::new(10);
+        estimator.update(42, Utc::now());
+
+        let mut initial = HashMap::new();
+        initial.insert(tp("orders", 0), RwLock::new(estimator));
+        let estimators = Arc::new(RwLock::new(initial));
+
+        let live: HashSet<TopicPartition> = [tp("orders", 0)].into_iter().collect();
+
+        PartitionOffsetsRegister::reconcile_estimators(&estimators, &live, 10, LagEstimationMode::Interpolation).await;
+
+        let guard = estimators.read().await;
+        let est = guard.get(&tp("orders", 0)).unwrap().read().await;
+        assert_eq!(est.latest_offset().unwrap().offset, 42);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+
+use super::errors::PartitionOffsetsResult;
+use crate::kafka_types::TopicPartition;
+
+/// What's persisted for a single [`TopicPartition`]: its bounded `(offset, UTC TS)` history,
+/// in the same oldest-to-newest order the [`super::lag_estimator::PartitionLagEstimator`] holds it.
+pub type OffsetsSnapshot = HashMap<TopicPartition, Vec<(u64, DateTime<Utc>)>>;
+
+/// A pluggable backend that can persist and restore a [`PartitionOffsetsRegister`]'s history,
+/// so a freshly started process doesn't have to re-warm its estimators from scratch.
+///
+/// [`PartitionOffsetsRegister`]: super::register::PartitionOffsetsRegister
+pub trait PartitionOffsetsSnapshotter: Send + Sync {
+    /// Persist the given snapshot, replacing whatever was previously saved.
+    fn save(&self, snapshot: &OffsetsSnapshot) -> PartitionOffsetsResult<()>;
+
+    /// Load the most recently saved snapshot, if any.
+    ///
+    /// Returns an empty [`OffsetsSnapshot`] if the backend has nothing saved yet
+    /// (e.g. first ever run), rather than an error.
+    fn load(&self) -> PartitionOffsetsResult<OffsetsSnapshot>;
+}
+
+/// A [`PartitionOffsetsSnapshotter`] that keeps a single JSON file on local disk.
+///
+/// This is the simplest backend: it's a fine default for a single-instance deployment,
+/// but doesn't help when the register runs behind multiple, interchangeable processes.
+pub struct FileSnapshotter {
+    path: PathBuf,
+}
+
+impl FileSnapshotter {
+    /// Create a new [`Self`] that reads from and writes to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl PartitionOffsetsSnapshotter for FileSnapshotter {
+    fn save(&self, snapshot: &OffsetsSnapshot) -> PartitionOffsetsResult<()> {
+        // `TopicPartition` isn't a string, so it can't serialize as a JSON object key: go
+        // through a `Vec` of entries instead, which serde_json is happy to encode as a JSON array.
+        let entries: Vec<(&TopicPartition, &Vec<(u64, DateTime<Utc>)>)> = snapshot.iter().collect();
+        let serialized = serde_json::to_vec(&entries)?;
+
+        // Write to a temp file first and rename, so a crash mid-write can never
+        // leave behind a truncated/corrupt snapshot for the next `load()`.
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serialized)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    fn load(&self) -> PartitionOffsetsResult<OffsetsSnapshot> {
+        if !self.path.exists() {
+            return Ok(OffsetsSnapshot::new());
+        }
+
+        let bytes = fs::read(&self.path)?;
+        let entries: Vec<(TopicPartition, Vec<(u64, DateTime<Utc>)>)> = serde_json::from_slice(&bytes)?;
+
+        Ok(entries.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn ts(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    /// A fresh path under the OS temp dir, unique per test process/call so concurrent test runs
+    /// can't trip over each other's snapshot file.
+    fn temp_snapshot_path() -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        std::env::temp_dir().join(format!("kommitted-snapshot-test-{}-{}.json", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    #[test]
+    fn load_returns_an_empty_snapshot_when_nothing_was_ever_saved() {
+        let snapshotter = FileSnapshotter::new(temp_snapshot_path());
+
+        let loaded = snapshotter.load().unwrap();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn save_then_load_round_trips_the_snapshot() {
+        let path = temp_snapshot_path();
+        let snapshotter = FileSnapshotter::new(&path);
+
+        let mut snapshot = OffsetsSnapshot::new();
+        snapshot.insert(
+            TopicPartition {
+                topic: "orders".to_string(),
+                partition: 0,
+            },
+            vec![(100, ts(0)), (200, ts(10))],
+        );
+        snapshot.insert(
+            TopicPartition {
+                topic: "orders".to_string(),
+                partition: 1,
+            },
+            vec![(50, ts(5))],
+        );
+
+        snapshotter.save(&snapshot).unwrap();
+        let loaded = snapshotter.load().unwrap();
+
+        assert_eq!(loaded, snapshot);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn save_overwrites_a_previously_saved_snapshot() {
+        let path = temp_snapshot_path();
+        let snapshotter = FileSnapshotter::new(&path);
+
+        let tp = TopicPartition {
+            topic: "orders".to_string(),
+            partition: 0,
+        };
+
+        let mut first = OffsetsSnapshot::new();
+        first.insert(tp.clone(), vec![(100, ts(0))]);
+        snapshotter.save(&first).unwrap();
+
+        let mut second = OffsetsSnapshot::new();
+        second.insert(tp, vec![(200, ts(10))]);
+        snapshotter.save(&second).unwrap();
+
+        let loaded = snapshotter.load().unwrap();
+        assert_eq!(loaded, second);
+
+        fs::remove_file(&path).unwrap();
+    }
+}